@@ -0,0 +1,164 @@
+// --- Externalized Configuration ---
+//
+// Every operational parameter used to be baked into `main` and the triage
+// closure: `brain_url`, `upstream_url`, the bind address, and the
+// suspicious-pattern list. This loads them from a TOML file instead (path
+// given via `--config <path>`, the `JIRACHI_CONFIG` env var, or a
+// `config.toml` default) so operators can retune detection and endpoints
+// without a recompile. `load` is re-run on SIGHUP and the result swapped
+// into `AppState::config` atomically.
+
+use regex::Regex;
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::info;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RawConfig {
+    pub brain_url: String,
+    pub upstream_url: String,
+    pub bind_addr: String,
+    #[serde(default)]
+    pub trust_forwarded_headers: bool,
+    #[serde(default = "default_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    #[serde(default = "default_request_timeout_ms")]
+    pub request_timeout_ms: u64,
+    #[serde(default = "default_suspicious_patterns")]
+    pub suspicious_patterns: Vec<String>,
+    // Shared HMAC secret for the signed-command envelope with the General.
+    // Prefer the `JIRACHI_BRAIN_SHARED_KEY` env var over this field so the
+    // secret doesn't have to live in the checked-in TOML; see
+    // `resolve_brain_shared_key`.
+    #[serde(default)]
+    pub brain_shared_key: Option<String>,
+}
+
+fn default_connect_timeout_ms() -> u64 {
+    2_000
+}
+
+fn default_request_timeout_ms() -> u64 {
+    5_000
+}
+
+fn default_suspicious_patterns() -> Vec<String> {
+    vec![
+        "admin".to_string(),
+        "UNION".to_string(),
+        "%27".to_string(),
+        "siren_test".to_string(),
+    ]
+}
+
+impl Default for RawConfig {
+    fn default() -> Self {
+        Self {
+            brain_url: "http://127.0.0.1:8000/analyze_threat".to_string(),
+            upstream_url: "http://127.0.0.1:5000".to_string(),
+            bind_addr: "0.0.0.0:6161".to_string(),
+            trust_forwarded_headers: false,
+            connect_timeout_ms: default_connect_timeout_ms(),
+            request_timeout_ms: default_request_timeout_ms(),
+            suspicious_patterns: default_suspicious_patterns(),
+            brain_shared_key: None,
+        }
+    }
+}
+
+/// Resolve the HMAC shared secret: the `JIRACHI_BRAIN_SHARED_KEY` env var
+/// wins (keeps the secret out of the checked-in TOML), then the config
+/// file's `brain_shared_key`. Neither set is a hard error -- this key is
+/// what `verify_judgment` uses to authenticate every BLOCK/DECEIVE command
+/// from the General, so silently falling back to a known demo key would
+/// make that verification security theater rather than a real control.
+fn resolve_brain_shared_key(from_file: Option<String>) -> anyhow::Result<Vec<u8>> {
+    if let Ok(key) = std::env::var("JIRACHI_BRAIN_SHARED_KEY") {
+        return Ok(key.into_bytes());
+    }
+    if let Some(key) = from_file {
+        return Ok(key.into_bytes());
+    }
+    Err(anyhow::anyhow!(
+        "brain_shared_key not set: provide JIRACHI_BRAIN_SHARED_KEY or config.toml's brain_shared_key"
+    ))
+}
+
+/// The live, compiled form of `RawConfig`. The `reqwest::Client` is rebuilt
+/// here too so a reload that changes the timeouts takes effect immediately
+/// rather than only on the next restart.
+pub struct Config {
+    pub brain_url: String,
+    pub upstream_url: String,
+    pub bind_addr: String,
+    pub trust_forwarded_headers: bool,
+    pub http_client: Client,
+    pub brain_shared_key: Vec<u8>,
+    triage_patterns: Vec<Regex>,
+}
+
+impl Config {
+    pub fn compile(raw: RawConfig) -> anyhow::Result<Self> {
+        let triage_patterns = raw
+            .suspicious_patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let http_client = Client::builder()
+            .connect_timeout(Duration::from_millis(raw.connect_timeout_ms))
+            .timeout(Duration::from_millis(raw.request_timeout_ms))
+            .build()?;
+
+        let brain_shared_key = resolve_brain_shared_key(raw.brain_shared_key)?;
+
+        Ok(Self {
+            brain_url: raw.brain_url,
+            upstream_url: raw.upstream_url,
+            bind_addr: raw.bind_addr,
+            trust_forwarded_headers: raw.trust_forwarded_headers,
+            http_client,
+            brain_shared_key,
+            triage_patterns,
+        })
+    }
+
+    /// The Sentinel's local triage, driven by the user-editable pattern list
+    /// instead of hard-coded substring checks.
+    pub fn is_suspicious(&self, uri: &str, body: &str) -> bool {
+        self.triage_patterns
+            .iter()
+            .any(|pattern| pattern.is_match(uri) || pattern.is_match(body))
+    }
+}
+
+/// `--config <path>` wins, then `JIRACHI_CONFIG`, then a local `config.toml`.
+pub fn config_path() -> String {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            if let Some(path) = args.next() {
+                return path;
+            }
+        }
+    }
+    std::env::var("JIRACHI_CONFIG").unwrap_or_else(|_| "config.toml".to_string())
+}
+
+/// Read and compile the config at `path`. A missing file falls back to
+/// built-in defaults; those carry no `brain_shared_key`, so they only
+/// compile when `JIRACHI_BRAIN_SHARED_KEY` is set. A present-but-invalid
+/// (or unreadable) file is a hard error, since silently ignoring a broken
+/// config could mean running with none of the operator's intended triage rules.
+pub fn load(path: &str) -> anyhow::Result<Config> {
+    let raw: RawConfig = match std::fs::read_to_string(path) {
+        Ok(contents) => toml::from_str(&contents)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            info!("Config: {} not found ({}), using built-in defaults", path, e);
+            RawConfig::default()
+        }
+        Err(e) => return Err(e.into()),
+    };
+    Config::compile(raw)
+}
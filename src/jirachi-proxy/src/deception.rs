@@ -0,0 +1,180 @@
+// --- The Deception Engine ---
+//
+// `honeypot_handler` used to return the same one-shot fake DB error to every
+// visitor -- a tell any competent attacker spots instantly. This tracks a
+// session per trapped attacker and walks them through a scripted, internally
+// consistent fake environment instead, logging every transition so analysts
+// can replay the full path afterwards.
+
+use metrics::counter;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::time::interval;
+use tracing::{error, info};
+use uuid::Uuid;
+
+// How long a trap session survives without a fresh hit before it's evicted,
+// mirroring the reputation map's REPUTATION_TTL and the XDP BLOCKLIST's
+// BLOCK_TTL. Without this, a script hitting `/trap` with no (or a fresh)
+// cookie on every request -- which is exactly what mints a new session --
+// would grow `sessions` forever.
+const SESSION_TTL: Duration = Duration::from_secs(600);
+const SESSION_EVICTION_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A step in the scripted fake environment. Each stage is a distinct decoy
+/// surface (a fake login failure, a fake admin dashboard, ...); `next`
+/// advances an attacker one step deeper, and the terminal stage just keeps
+/// stalling so a long engagement doesn't run out of script.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    Login,
+    Dashboard,
+    Database,
+    FileSystem,
+}
+
+impl Stage {
+    fn next(self) -> Stage {
+        match self {
+            Stage::Login => Stage::Dashboard,
+            Stage::Dashboard => Stage::Database,
+            Stage::Database => Stage::FileSystem,
+            Stage::FileSystem => Stage::FileSystem,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Stage::Login => "login",
+            Stage::Dashboard => "dashboard",
+            Stage::Database => "database",
+            Stage::FileSystem => "filesystem",
+        }
+    }
+
+    fn response(self) -> Value {
+        match self {
+            Stage::Login => json!({
+                "error": "Fatal DB Error",
+                "debug_trace": "SELECT * FROM users WHERE admin = 1 failed.",
+                "suggestion": "Please contact sysadmin.",
+            }),
+            Stage::Dashboard => json!({
+                "view": "admin_dashboard",
+                "tables": ["users", "invoices", "api_keys"],
+            }),
+            Stage::Database => json!({
+                "view": "table_listing",
+                "table": "api_keys",
+                "rows": [
+                    { "id": 1, "key": "sk_live_deadbeefcafebabe", "owner": "billing-service" },
+                    { "id": 2, "key": "sk_live_0ff1ce0ff1ce0ff1", "owner": "internal-tools" },
+                ],
+            }),
+            Stage::FileSystem => json!({
+                "view": "file_browser",
+                "cwd": "/var/www/app/config",
+                "entries": ["database.yml", "secrets.enc", ".env.production"],
+            }),
+        }
+    }
+}
+
+struct Session {
+    client_ip: IpAddr,
+    stage: Stage,
+    last_seen: Instant,
+}
+
+/// Per-process store of trapped-attacker sessions, plus an append-only event
+/// log so an analyst can reconstruct an attacker's whole path through the
+/// decoy afterwards.
+pub struct DeceptionEngine {
+    sessions: Mutex<HashMap<Uuid, Session>>,
+    event_log: Mutex<File>,
+}
+
+impl DeceptionEngine {
+    pub fn new(log_path: &str) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(log_path)?;
+        Ok(Self {
+            sessions: Mutex::new(HashMap::new()),
+            event_log: Mutex::new(file),
+        })
+    }
+
+    /// Look up the session behind `cookie_session_id` (minting one on first
+    /// contact), advance it one stage, and return the session id to stamp
+    /// back onto the cookie along with the next scripted response. A cookie
+    /// that resolves to a real session but a different `client_ip` is
+    /// treated as a fresh attacker, not a continuation -- sessions are tied
+    /// to the IP they started on so a stolen/shared `siren_session` cookie
+    /// can't splice someone else's trap state onto a different origin.
+    pub async fn handle_trap(&self, cookie_session_id: Option<Uuid>, client_ip: IpAddr) -> (Uuid, Value) {
+        let mut sessions = self.sessions.lock().await;
+
+        let id = match cookie_session_id.filter(|id| {
+            sessions.get(id).is_some_and(|session| session.client_ip == client_ip)
+        }) {
+            Some(id) => id,
+            None => {
+                let id = Uuid::new_v4();
+                sessions.insert(id, Session { client_ip, stage: Stage::Login, last_seen: Instant::now() });
+                id
+            }
+        };
+
+        let session = sessions.get_mut(&id).unwrap();
+        let stage = session.stage;
+        session.stage = stage.next();
+        session.last_seen = Instant::now();
+        drop(sessions);
+
+        self.log_event(id, client_ip, stage).await;
+        (id, stage.response())
+    }
+
+    async fn log_event(&self, session_id: Uuid, client_ip: IpAddr, stage: Stage) {
+        counter!("siren_honeypot_transitions_total", "stage" => stage.label()).increment(1);
+
+        let event = json!({
+            "session_id": session_id.to_string(),
+            "client_ip": client_ip.to_string(),
+            "stage": stage.label(),
+        });
+
+        let mut log = self.event_log.lock().await;
+        if let Err(e) = writeln!(log, "{}", event) {
+            error!("Deception: failed to persist honeypot event: {}", e);
+        }
+    }
+
+    async fn evict_expired(&self) {
+        let mut sessions = self.sessions.lock().await;
+        let before = sessions.len();
+        sessions.retain(|_, session| session.last_seen.elapsed() < SESSION_TTL);
+        let evicted = before - sessions.len();
+        if evicted > 0 {
+            info!("Deception: evicted {} expired honeypot session(s)", evicted);
+        }
+    }
+}
+
+/// Background task: drop any trap session that hasn't been touched within
+/// `SESSION_TTL`, the same eviction shape as `firewall::spawn_eviction_task`
+/// and `spawn_reputation_eviction_task` in `main.rs`.
+pub fn spawn_eviction_task(engine: Arc<DeceptionEngine>) {
+    tokio::spawn(async move {
+        let mut ticker = interval(SESSION_EVICTION_INTERVAL);
+        loop {
+            ticker.tick().await;
+            engine.evict_expired().await;
+        }
+    });
+}
@@ -0,0 +1,126 @@
+// --- The XDP Control Plane ---
+//
+// Bridges L7 judgments (the Axum proxy) into L3/L4 enforcement (the
+// `xdp_firewall` program in `ebpf_shield`). A BLOCK verdict lands here, gets
+// pushed into the shared `BLOCKLIST` map, and from then on the kernel drops
+// the attacker's packets before they ever reach the async runtime.
+
+use aya::maps::HashMap as AyaHashMap;
+use aya::programs::{Xdp, XdpFlags};
+use aya::{Ebpf, EbpfLoader};
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::time::interval;
+use tracing::{error, info, warn};
+
+const BLOCKLIST_MAP_NAME: &str = "BLOCKLIST";
+const XDP_PROGRAM_NAME: &str = "xdp_firewall";
+// Compiled by `cargo build-ebpf` in the `ebpf_shield` crate.
+const XDP_OBJ_PATH: &str = "./target/bpfel-unknown-none/release/ebpf_shield";
+
+// How long a BLOCK verdict stays enforced before it's evicted and the host
+// gets another chance. Temporary blocks, not permanent bans.
+const BLOCK_TTL: Duration = Duration::from_secs(600);
+const EVICTION_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Shared handle to the live `BLOCKLIST` XDP map, plus the bookkeeping the
+/// map itself can't hold (the map value is just a hit counter, not a
+/// timestamp, so TTL eviction needs a side table).
+pub struct Blocklist {
+    map: Mutex<AyaHashMap<aya::maps::MapData, u32, u32>>,
+    inserted_at: Mutex<HashMap<u32, Instant>>,
+}
+
+impl Blocklist {
+    /// Insert (or bump the hit count for) an attacker IP. Fail-open: if the
+    /// kernel map write fails we log and keep serving -- a firewall bug
+    /// should never become an outage.
+    pub async fn block(&self, ip: Ipv4Addr) {
+        let key = u32::from(ip);
+        let mut map = self.map.lock().await;
+        let hits = map.get(&key, 0).unwrap_or(0) + 1;
+        if let Err(e) = map.insert(key, hits, 0) {
+            error!("Firewall: failed to insert {} into BLOCKLIST: {}", ip, e);
+            return;
+        }
+        drop(map);
+        self.inserted_at.lock().await.insert(key, Instant::now());
+        warn!("Firewall: BLOCKLIST += {} (hits={})", ip, hits);
+    }
+
+    /// Snapshot current entries for the `/blocklist` debug route.
+    pub async fn snapshot(&self) -> Vec<(Ipv4Addr, u32)> {
+        let map = self.map.lock().await;
+        map.iter()
+            .filter_map(|entry| entry.ok())
+            .map(|(key, hits)| (Ipv4Addr::from(key), hits))
+            .collect()
+    }
+
+    async fn evict_expired(&self) {
+        let mut inserted_at = self.inserted_at.lock().await;
+        let expired: Vec<u32> = inserted_at
+            .iter()
+            .filter(|(_, at)| at.elapsed() >= BLOCK_TTL)
+            .map(|(key, _)| *key)
+            .collect();
+        if expired.is_empty() {
+            return;
+        }
+        let mut map = self.map.lock().await;
+        for key in expired {
+            let _ = map.remove(&key);
+            inserted_at.remove(&key);
+            info!("Firewall: BLOCKLIST entry for {} expired", Ipv4Addr::from(key));
+        }
+    }
+}
+
+/// Keeps the loaded `Ebpf` object (and therefore the attached XDP program)
+/// alive for the life of the process; dropping it detaches the program.
+pub struct FirewallHandle {
+    _bpf: Ebpf,
+    pub blocklist: Arc<Blocklist>,
+}
+
+/// Load `xdp_firewall`, attach it to `iface`, and start the background TTL
+/// eviction task. Returns `Err` if the object can't be loaded or the
+/// interface can't be found -- callers should treat that as non-fatal in
+/// environments without the right capabilities (dev boxes, CI).
+pub fn load(iface: &str) -> anyhow::Result<FirewallHandle> {
+    let mut bpf = EbpfLoader::new().load_file(XDP_OBJ_PATH)?;
+
+    let program: &mut Xdp = bpf
+        .program_mut(XDP_PROGRAM_NAME)
+        .ok_or_else(|| anyhow::anyhow!("ebpf_shield object has no program named {}", XDP_PROGRAM_NAME))?
+        .try_into()?;
+    program.load()?;
+    program.attach(iface, XdpFlags::default())?;
+
+    let map = AyaHashMap::try_from(
+        bpf.take_map(BLOCKLIST_MAP_NAME)
+            .ok_or_else(|| anyhow::anyhow!("ebpf_shield object has no map named {}", BLOCKLIST_MAP_NAME))?,
+    )?;
+    let blocklist = Arc::new(Blocklist {
+        map: Mutex::new(map),
+        inserted_at: Mutex::new(HashMap::new()),
+    });
+
+    spawn_eviction_task(blocklist.clone());
+    info!("Firewall: xdp_firewall attached to {}", iface);
+
+    Ok(FirewallHandle { _bpf: bpf, blocklist })
+}
+
+fn spawn_eviction_task(blocklist: Arc<Blocklist>) {
+    tokio::spawn(async move {
+        let mut ticker = interval(EVICTION_INTERVAL);
+        loop {
+            ticker.tick().await;
+            blocklist.evict_expired().await;
+        }
+    });
+}
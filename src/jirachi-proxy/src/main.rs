@@ -1,17 +1,36 @@
 
 use axum::{
-    body::Body,
-    extract::{Request, State},
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
     response::{Response, IntoResponse},
-    routing::{any, post, get}, // Added get/post for trap
-    Router, Json, // Added Json
+    routing::{any, get},
+    Router, Json,
 };
-use reqwest::Client;
+use arc_swap::ArcSwap;
+use hmac::{Hmac, Mac};
+use metrics::{counter, histogram};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use sha2::Sha256;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::Mutex;
 use tracing::{info, warn, error};
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use serde_json::json; // Added json macro
+use uuid::Uuid;
+
+mod config;
+mod deception;
+mod firewall;
+mod resilience;
+use config::Config;
+use deception::DeceptionEngine;
+use firewall::Blocklist;
+use resilience::CircuitBreaker;
 
 // --- JSON Artifact Schemas ---
 
@@ -20,6 +39,10 @@ struct ThreatJudgment {
     artifact_type: String,
     threat_level: String,
     command: Command,
+    // HMAC-SHA256 over the canonical JSON of `command` plus the escalation
+    // nonce -- see `verify_judgment`. Without this, anything that can reach
+    // or MITM `brain_url` can inject a BLOCK/DECEIVE and hijack routing.
+    signature: Vec<u8>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -28,96 +51,460 @@ struct Command {
     redirect_target: Option<String>,
 }
 
+type HmacSha256 = Hmac<Sha256>;
+
+/// Verify that `judgment.signature` is a valid HMAC-SHA256 tag, under the
+/// shared `brain_shared_key`, over the canonical JSON of `judgment.command`
+/// concatenated with the escalation `nonce`. A mismatch means the response
+/// wasn't produced (or wasn't produced unmodified) by a holder of the key.
+fn verify_judgment(judgment: &ThreatJudgment, nonce: &str, key: &[u8]) -> bool {
+    let canonical_command = match serde_json::to_vec(&judgment.command) {
+        Ok(bytes) => bytes,
+        Err(_) => return false,
+    };
+
+    let mut mac = match HmacSha256::new_from_slice(key) {
+        Ok(mac) => mac,
+        Err(_) => return false,
+    };
+    mac.update(&canonical_command);
+    mac.update(nonce.as_bytes());
+
+    mac.verify_slice(&judgment.signature).is_ok()
+}
+
 // --- App State ---
 
 struct AppState {
-    brain_url: String,
-    upstream_url: String,
-    http_client: Client,
+    // Hot-reloadable: brain/upstream endpoints, trusted-proxy setting,
+    // timeouts (baked into the bundled http_client), the shared HMAC secret,
+    // and triage patterns all swap atomically on SIGHUP. The bind address is
+    // only read at startup; a reload does not rebind the listener.
+    config: ArcSwap<Config>,
+    metrics_handle: PrometheusHandle,
+    blocklist: Option<Arc<Blocklist>>,
+    // Suspicious-hit count plus the last time it was bumped, so a stale
+    // count can decay instead of accumulating forever.
+    reputation: Mutex<HashMap<IpAddr, (u32, Instant)>>,
+    deception: Arc<DeceptionEngine>,
+    brain_breaker: CircuitBreaker,
+    upstream_breaker: CircuitBreaker,
+}
+
+// Retry budget for idempotent calls: a couple of quick retries after the
+// first attempt, not a hammering loop.
+const RETRY_MAX_RETRIES: u32 = 2;
+const RETRY_BASE_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+
+const BRAIN_FAILURE_THRESHOLD: u32 = 3;
+const BRAIN_BREAKER_COOLDOWN: std::time::Duration = std::time::Duration::from_secs(30);
+const UPSTREAM_FAILURE_THRESHOLD: u32 = 5;
+const UPSTREAM_BREAKER_COOLDOWN: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// HTTP methods safe to blindly retry -- no side effects from sending the
+/// same request twice.
+fn is_idempotent(method: &reqwest::Method) -> bool {
+    matches!(
+        *method,
+        reqwest::Method::GET | reqwest::Method::HEAD | reqwest::Method::PUT | reqwest::Method::DELETE | reqwest::Method::OPTIONS
+    )
+}
+
+// Repeat offenders escalate faster than the brain round-trip: once a peer's
+// suspicious-hit count crosses this, the proxy blocks without waiting on the General.
+const REPUTATION_BLOCK_THRESHOLD: u32 = 3;
+
+// How long a suspicious-hit count survives without a fresh hit before it
+// decays back to zero, mirroring the XDP BLOCKLIST's BLOCK_TTL. Without this,
+// a NAT/LB egress IP shared by many clients (the default when
+// `trust_forwarded_headers` is off) would have its count ratchet up forever,
+// permanently block-listing everyone behind it over three unrelated hits.
+const REPUTATION_TTL: std::time::Duration = std::time::Duration::from_secs(600);
+const REPUTATION_EVICTION_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Resolve the IP actually responsible for the request. Behind a trusted
+/// proxy, the wire-level peer address is the load balancer, not the client,
+/// so `X-Forwarded-For` (falling back to `X-Real-IP`) is consulted instead --
+/// but only when `trust_forwarded_headers` is set, since both headers are
+/// trivially spoofable by anyone who can reach this proxy directly.
+fn resolve_client_ip(peer: SocketAddr, headers: &HeaderMap, trust_forwarded_headers: bool) -> IpAddr {
+    if trust_forwarded_headers {
+        // XFF is appended to left-to-right as it passes through proxies; the
+        // right-most entry is the one added by the hop closest to us, i.e.
+        // the address our (trusted) load balancer actually saw.
+        if let Some(ip) = headers
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse::<IpAddr>().ok())
+        {
+            return ip;
+        }
+        if let Some(ip) = headers
+            .get("x-real-ip")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|ip| ip.trim().parse::<IpAddr>().ok())
+        {
+            return ip;
+        }
+    }
+    peer.ip()
+}
+
+/// Bump the suspicious-hit counter for `ip`, resetting its TTL, and return
+/// the new total.
+async fn bump_reputation(state: &AppState, ip: IpAddr) -> u32 {
+    let mut reputation = state.reputation.lock().await;
+    let now = Instant::now();
+    let entry = reputation.entry(ip).or_insert((0, now));
+    entry.0 += 1;
+    entry.1 = now;
+    entry.0
+}
+
+/// Background task: drop any reputation entry that hasn't been bumped within
+/// `REPUTATION_TTL`, the same eviction shape as the XDP BLOCKLIST in
+/// `firewall.rs` so an IP's count can't outlive the enforcement it feeds.
+fn spawn_reputation_eviction_task(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(REPUTATION_EVICTION_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let mut reputation = state.reputation.lock().await;
+            reputation.retain(|_, (_, last_hit)| last_hit.elapsed() < REPUTATION_TTL);
+        }
+    });
+}
+
+// --- Metrics ---
+
+// Mirrors the method/controller/result convention: every decision path through
+// the shield bumps exactly one of these, so `siren_requests_total` sums to the
+// total request count.
+fn track_request(method: &str, controller: &str, decision: &str) {
+    counter!(
+        "siren_requests_total",
+        "method" => method.to_string(),
+        "controller" => controller.to_string(),
+        "decision" => decision.to_string(),
+    )
+    .increment(1);
+}
+
+async fn metrics_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    state.metrics_handle.render()
+}
+
+// --- Blocklist Debug Route ---
+
+async fn blocklist_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match &state.blocklist {
+        Some(blocklist) => {
+            let entries: Vec<_> = blocklist
+                .snapshot()
+                .await
+                .into_iter()
+                .map(|(ip, hits)| json!({ "ip": ip.to_string(), "hits": hits }))
+                .collect();
+            Json(json!({ "blocklist": entries }))
+        }
+        None => Json(json!({ "blocklist": [], "note": "xdp firewall not attached" })),
+    }
+}
+
+/// Pull the `siren_session` cookie value, if present and a valid UUID, out
+/// of the request's `Cookie` header.
+fn extract_session_cookie(headers: &HeaderMap) -> Option<Uuid> {
+    headers
+        .get(axum::http::header::COOKIE)?
+        .to_str()
+        .ok()?
+        .split(';')
+        .find_map(|kv| {
+            let mut parts = kv.trim().splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some("siren_session"), Some(value)) => Uuid::parse_str(value).ok(),
+                _ => None,
+            }
+        })
 }
 
 // --- THE HONEYPOT HANDLER (SIREN) ---
-async fn honeypot_handler(req: Request<Body>) -> impl IntoResponse {
-    warn!("!! [SIREN] ALERT: Attacker trapped in Honeypot!");
+/// Fail-safe response for when a judgment from the General can't be trusted
+/// -- circuit open, unreachable, unparsable, or signature-invalid -- so
+/// suspicious traffic never falls through to plain forwarding just because
+/// the brain is unavailable or compromised.
+fn brain_fail_safe_response() -> Response<Body> {
+    Response::builder()
+        .status(axum::http::StatusCode::TEMPORARY_REDIRECT)
+        .header("Location", "/trap")
+        .body(Body::from("Redirecting for debug..."))
+        .unwrap()
+}
+
+async fn honeypot_handler(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    req: Request<Body>,
+) -> impl IntoResponse {
+    let client_ip = resolve_client_ip(peer, req.headers(), state.config.load().trust_forwarded_headers);
+    let cookie_session_id = extract_session_cookie(req.headers());
+    warn!("!! [SIREN] ALERT: {} trapped in Honeypot!", client_ip);
     warn!("!! [SIREN] Headers: {:?}", req.headers());
-    
-    (
-        axum::http::StatusCode::OK, 
-        Json(json!({
-            "error": "Fatal DB Error",
-            "debug_trace": "SELECT * FROM users WHERE admin = 1 failed.", // Fake info
-            "suggestion": "Please contact sysadmin."
-        }))
-    )
+
+    counter!("siren_honeypot_hits_total").increment(1);
+    track_request(req.method().as_str(), "honeypot", "deceive");
+
+    let (session_id, body) = state.deception.handle_trap(cookie_session_id, client_ip).await;
+
+    Response::builder()
+        .status(axum::http::StatusCode::OK)
+        .header(axum::http::header::CONTENT_TYPE, "application/json")
+        .header("Set-Cookie", format!("siren_session={}; Path=/; HttpOnly", session_id))
+        .body(Body::from(serde_json::to_vec(&body).unwrap_or_default()))
+        .unwrap()
+}
+
+// Hop-by-hop headers that must not be blindly replayed onto the upstream request.
+const HOP_BY_HOP_HEADERS: &[&str] = &["host", "connection", "transfer-encoding"];
+
+fn strip_hop_by_hop(headers: &HeaderMap) -> HeaderMap {
+    let mut out = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers.iter() {
+        if !HOP_BY_HOP_HEADERS.contains(&name.as_str()) {
+            // `append`, not `insert`: a repeated header (e.g. multiple
+            // `Set-Cookie`) is multiple distinct entries in `headers.iter()`,
+            // and `insert` would replace all but the last one.
+            out.append(name.clone(), value.clone());
+        }
+    }
+    out
+}
+
+// Framing headers describe how the *upstream* connection delivered its bytes,
+// not the `Body::from(bytes)` axum rebuilds them into here (which is always a
+// single chunk). Replaying these verbatim produces a response whose headers lie
+// about its own body. `Content-Encoding` is kept: reqwest is built without
+// decompression, so the buffered bytes are still encoded as upstream sent them.
+const RESPONSE_FRAMING_HEADERS: &[&str] = &[
+    "connection",
+    "transfer-encoding",
+    "content-length",
+];
+
+fn strip_response_framing(headers: &HeaderMap) -> HeaderMap {
+    let mut out = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers.iter() {
+        if !RESPONSE_FRAMING_HEADERS.contains(&name.as_str()) {
+            // `append`, not `insert` -- see `strip_hop_by_hop`: a repeated
+            // `Set-Cookie` from upstream must survive as multiple entries,
+            // not collapse to the last one.
+            out.append(name.clone(), value.clone());
+        }
+    }
+    out
 }
 
+// 10 MiB cap on buffered bodies, matching the kind of payload a triage/forward pass should ever need to hold.
+const MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+
 // --- The Proxy Handler ---
 
 async fn proxy_handler(
     State(state): State<Arc<AppState>>,
-    mut req: Request<Body>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    req: Request<Body>,
 ) -> impl IntoResponse {
+    let cfg = state.config.load();
     let uri = req.uri().to_string();
     let method = req.method().to_string();
-    
-    // 1. The Sentinel (Local Triage)
-    // Adding 'siren_test' for the demo
-    let is_suspicious = uri.contains("admin") || uri.contains("UNION") || uri.contains("%27") || uri.contains("siren_test");
-    
+    let headers = strip_hop_by_hop(req.headers());
+    let client_ip = resolve_client_ip(peer, req.headers(), cfg.trust_forwarded_headers);
+
+    // Buffer the body once: the sentinel inspects it below, and the forwarding
+    // call at the bottom reuses the same bytes instead of re-reading the stream.
+    let body_bytes = match to_bytes(req.into_body(), MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            // Over the cap (or some other read error) -- forwarding an empty
+            // body in its place would silently corrupt a legitimate large
+            // POST/PUT/PATCH, so reject it instead of swallowing it.
+            warn!("Rejecting body from {}: {}", client_ip, e);
+            track_request(&method, "proxy", "reject");
+            return Response::builder()
+                .status(axum::http::StatusCode::PAYLOAD_TOO_LARGE)
+                .body(Body::from("Jirachi Shield: Request body too large."))
+                .unwrap();
+        }
+    };
+    let body_text = String::from_utf8_lossy(&body_bytes);
+
+    // Set once a verdict is reached, then used for the single siren_requests_total bump below.
+    let mut decision = "allow";
+
+    // 1. The Sentinel (Local Triage), driven by the operator-editable pattern list.
+    let is_suspicious = cfg.is_suspicious(&uri, &body_text);
+
     if is_suspicious {
-        info!("Sentinel: Detected suspicious pattern: {}", uri);
-        
+        decision = "forward"; // the General's BLOCK/DECEIVE paths return early with their own decision
+        info!("Sentinel: Detected suspicious pattern from {}: {}", client_ip, uri);
+
+        let reputation_hits = bump_reputation(&state, client_ip).await;
+
+        // Repeat offender: don't even wait on the General, block now. This is
+        // a local-heuristic block, not a brain-confirmed one -- tag it
+        // distinctly (`block_reputation` vs `block`) so operators can tell
+        // the two apart, since this path never gets HMAC-verified sign-off
+        // from the General and can over-trigger behind a shared NAT/LB.
+        if reputation_hits >= REPUTATION_BLOCK_THRESHOLD {
+            warn!("Reputation: {} crossed the block threshold ({} hits)", client_ip, reputation_hits);
+            track_request(&method, "proxy", "block_reputation");
+            if let (Some(blocklist), IpAddr::V4(ip)) = (&state.blocklist, client_ip) {
+                blocklist.block(ip).await;
+            }
+            return Response::builder()
+                .status(403)
+                .body(Body::from("Jirachi Shield: Request Blocked by Command."))
+                .unwrap();
+        }
+
         // 2. The General (Escalation)
+        if !state.brain_breaker.is_call_permitted().await {
+            // Fail-safe: with the brain circuit open we can't get a judgment at
+            // all, so suspicious traffic defaults to DECEIVE rather than being
+            // silently forwarded straight through.
+            warn!("Brain circuit open for {}; defaulting to DECEIVE", uri);
+            track_request(&method, "proxy", "deceive");
+            return brain_fail_safe_response();
+        }
+
+        let nonce = Uuid::new_v4().to_string();
         let trace = format!("{} {}", method, uri);
         let payload = serde_json::json!({
             "trace": trace,
-            "slm_score": 0.9
+            "slm_score": 0.9,
+            "client_ip": client_ip.to_string(),
+            "reputation_hits": reputation_hits,
+            "nonce": nonce,
         });
 
-        match state.http_client.post(&state.brain_url).json(&payload).send().await {
+        // The General only ever reads `payload` and returns a judgment, so a
+        // timed-out attempt is safe to retry.
+        let brain_result = resilience::retry_idempotent(RETRY_MAX_RETRIES, RETRY_BASE_DELAY, || {
+            cfg.http_client.post(&cfg.brain_url).json(&payload).send()
+        })
+        .await;
+
+        match brain_result {
             Ok(resp) => {
-                if let Ok(judgment) = resp.json::<ThreatJudgment>().await {
-                    info!("General's Judgment: {:?}", judgment);
-                    
-                    if judgment.command.action == "BLOCK" {
-                        return Response::builder()
-                            .status(403)
-                            .body(Body::from("Jirachi Shield: Request Blocked by Command."))
-                            .unwrap();
+                match resp.json::<ThreatJudgment>().await {
+                    Ok(judgment) => {
+                        state.brain_breaker.record_success().await;
+                        if !verify_judgment(&judgment, &nonce, &cfg.brain_shared_key) {
+                            // An unverified judgment is as untrustworthy as no
+                            // judgment at all -- fail safe rather than fall through.
+                            warn!("Signature verification failed for General's judgment; defaulting to DECEIVE");
+                            counter!("siren_signature_failures_total").increment(1);
+                            track_request(&method, "proxy", "deceive");
+                            return brain_fail_safe_response();
+                        }
+
+                        info!("General's Judgment: {:?}", judgment);
+
+                        if judgment.command.action == "BLOCK" {
+                            track_request(&method, "proxy", "block");
+                            if let (Some(blocklist), IpAddr::V4(ip)) = (&state.blocklist, client_ip) {
+                                blocklist.block(ip).await;
+                            }
+                            return Response::builder()
+                                .status(403)
+                                .body(Body::from("Jirachi Shield: Request Blocked by Command."))
+                                .unwrap();
+                        }
+
+                        // --- SIREN DECEPTION LOGIC ---
+                        if judgment.command.action == "DECEIVE" {
+                            info!("<< COMMAND: DECEIVE. Rerouting to Siren.");
+                            track_request(&method, "proxy", "deceive");
+                            return brain_fail_safe_response();
+                        }
                     }
-                    
-                    // --- SIREN DECEPTION LOGIC ---
-                    if judgment.command.action == "DECEIVE" {
-                        info!("<< COMMAND: DECEIVE. Rerouting to Siren.");
-                        return Response::builder()
-                            .status(axum::http::StatusCode::TEMPORARY_REDIRECT)
-                            .header("Location", "/trap") // Send them to the trap
-                            .body(Body::from("Redirecting for debug..."))
-                            .unwrap();
+                    Err(_) => {
+                        // Malformed/unparsable judgment: same fail-safe as an
+                        // unreachable brain -- don't silently forward instead.
+                        state.brain_breaker.record_failure().await;
+                        warn!("Unparsable judgment from General for {}; defaulting to DECEIVE", uri);
+                        track_request(&method, "proxy", "deceive");
+                        return brain_fail_safe_response();
                     }
                 }
             }
-            Err(e) => error!("Failed to reach General: {}", e),
+            Err(e) => {
+                error!("Failed to reach General: {}", e);
+                counter!("siren_brain_errors_total").increment(1);
+                state.brain_breaker.record_failure().await;
+                warn!("Brain unreachable for {}; defaulting to DECEIVE", uri);
+                track_request(&method, "proxy", "deceive");
+                return brain_fail_safe_response();
+            }
         }
     }
 
-    // 3. Forward to Consumer (Upstream)
-    let path = req.uri().path();
-    let query = req.uri().query().unwrap_or("");
-    let target_url = format!("{}{}{}", state.upstream_url, path, if query.is_empty() { "".to_string() } else { format!("?{}", query) });
+    track_request(&method, "proxy", decision);
+
+    // 3. Forward to Consumer (Upstream) -- faithfully, not just a bodyless GET.
+    let uri_parts: Vec<&str> = uri.splitn(2, '?').collect();
+    let path = uri_parts[0];
+    let query = uri_parts.get(1).copied().unwrap_or("");
+    let target_url = format!("{}{}{}", cfg.upstream_url, path, if query.is_empty() { "".to_string() } else { format!("?{}", query) });
+
+    let upstream_method = reqwest::Method::from_bytes(method.as_bytes()).unwrap_or(reqwest::Method::GET);
+
+    if !state.upstream_breaker.is_call_permitted().await {
+        warn!("Upstream circuit open for {}; failing fast", target_url);
+        return Response::builder()
+            .status(axum::http::StatusCode::SERVICE_UNAVAILABLE)
+            .body(Body::from("Jirachi Shield: Upstream unavailable."))
+            .unwrap();
+    }
+
+    let upstream_start = Instant::now();
+    let upstream_result = if is_idempotent(&upstream_method) {
+        resilience::retry_idempotent(RETRY_MAX_RETRIES, RETRY_BASE_DELAY, || {
+            cfg.http_client
+                .request(upstream_method.clone(), &target_url)
+                .headers(headers.clone())
+                .body(body_bytes.clone())
+                .send()
+        })
+        .await
+    } else {
+        cfg.http_client
+            .request(upstream_method, &target_url)
+            .headers(headers)
+            .body(body_bytes)
+            .send()
+            .await
+    };
+    histogram!("siren_upstream_latency_seconds").record(upstream_start.elapsed().as_secs_f64());
 
-    match state.http_client.get(&target_url).send().await {
+    match upstream_result {
         Ok(upstream_resp) => {
+             state.upstream_breaker.record_success().await;
              let status_u16 = upstream_resp.status().as_u16();
              let status = axum::http::StatusCode::from_u16(status_u16).unwrap_or(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+             let response_headers = strip_response_framing(upstream_resp.headers());
              let bytes = upstream_resp.bytes().await.unwrap_or_default();
-             
-             Response::builder()
+
+             let mut response = Response::builder()
                 .status(status)
                 .body(Body::from(bytes))
-                .unwrap()
+                .unwrap();
+             *response.headers_mut() = response_headers;
+             response
         }
         Err(e) => {
+             state.upstream_breaker.record_failure().await;
              Response::builder()
                 .status(502)
                 .body(Body::from(format!("Upstream Error: {}", e)))
@@ -131,21 +518,181 @@ async fn proxy_handler(
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt::init();
-    
+
+    let config_path = config::config_path();
+    let initial_config = config::load(&config_path).expect("failed to load initial config");
+    let bind_addr = initial_config.bind_addr.clone();
+    let config = ArcSwap::from_pointee(initial_config);
+
+    let metrics_handle = PrometheusBuilder::new()
+        .install_recorder()
+        .expect("failed to install Prometheus recorder");
+
+    // Fail-open: if the XDP program can't be loaded (no CAP_NET_ADMIN, no
+    // such interface -- e.g. a dev box) the shield still runs, just without
+    // kernel-level enforcement. `_firewall_handle` is kept alive for the
+    // rest of `main` -- dropping it detaches the XDP program.
+    let _firewall_handle = match firewall::load("eth0") {
+        Ok(handle) => Some(handle),
+        Err(e) => {
+            warn!("Firewall: xdp_firewall not attached, continuing without it: {}", e);
+            None
+        }
+    };
+    let blocklist = _firewall_handle.as_ref().map(|h| h.blocklist.clone());
+
+    let deception = Arc::new(
+        DeceptionEngine::new("siren_honeypot_events.log").expect("failed to open honeypot event log"),
+    );
+    deception::spawn_eviction_task(deception.clone());
+
     let state = Arc::new(AppState {
-        brain_url: "http://127.0.0.1:8000/analyze_threat".to_string(),
-        upstream_url: "http://127.0.0.1:5000".to_string(),
-        http_client: Client::new(),
+        config,
+        metrics_handle,
+        blocklist,
+        reputation: Mutex::new(HashMap::new()),
+        deception,
+        brain_breaker: CircuitBreaker::new("brain", BRAIN_FAILURE_THRESHOLD, BRAIN_BREAKER_COOLDOWN),
+        upstream_breaker: CircuitBreaker::new("upstream", UPSTREAM_FAILURE_THRESHOLD, UPSTREAM_BREAKER_COOLDOWN),
     });
 
+    spawn_reputation_eviction_task(state.clone());
+
+    // Hot reload: re-read and recompile the config on SIGHUP, swapping it in
+    // atomically so in-flight requests are never handed a half-updated config.
+    {
+        let state = state.clone();
+        let config_path = config_path.clone();
+        tokio::spawn(async move {
+            let mut sighup = signal(SignalKind::hangup()).expect("failed to register SIGHUP handler");
+            loop {
+                sighup.recv().await;
+                info!("Config: SIGHUP received, reloading {}", config_path);
+                match config::load(&config_path) {
+                    Ok(new_config) => {
+                        state.config.store(Arc::new(new_config));
+                        info!("Config: reloaded successfully");
+                    }
+                    Err(e) => error!("Config: reload failed, keeping previous config: {}", e),
+                }
+            }
+        });
+    }
+
     let app = Router::new()
         .route("/trap", any(honeypot_handler)) // The Siren Trap
+        .route("/metrics", get(metrics_handler)) // Prometheus scrape target
+        .route("/blocklist", get(blocklist_handler)) // Dump current XDP BLOCKLIST entries
         .route("/*path", any(proxy_handler))   // The Shield
         .with_state(state);
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 6161));
+    let addr: SocketAddr = bind_addr.parse().unwrap_or_else(|_| SocketAddr::from(([0, 0, 0, 0], 6161)));
     info!("Jirachi Proxy (Axum) listening on {}", addr);
-    
+
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_judgment(command: Command, nonce: &str, key: &[u8]) -> ThreatJudgment {
+        let canonical_command = serde_json::to_vec(&command).unwrap();
+        let mut mac = HmacSha256::new_from_slice(key).unwrap();
+        mac.update(&canonical_command);
+        mac.update(nonce.as_bytes());
+        let signature = mac.finalize().into_bytes().to_vec();
+        ThreatJudgment { artifact_type: "judgment".to_string(), threat_level: "high".to_string(), command, signature }
+    }
+
+    #[test]
+    fn verify_judgment_accepts_a_correctly_signed_command() {
+        let key = b"correct-horse-battery-staple";
+        let nonce = "nonce-1";
+        let command = Command { action: "BLOCK".to_string(), redirect_target: None };
+        let judgment = signed_judgment(command, nonce, key);
+
+        assert!(verify_judgment(&judgment, nonce, key));
+    }
+
+    #[test]
+    fn verify_judgment_rejects_a_wrong_key() {
+        let nonce = "nonce-1";
+        let command = Command { action: "BLOCK".to_string(), redirect_target: None };
+        let judgment = signed_judgment(command, nonce, b"signing-key");
+
+        assert!(!verify_judgment(&judgment, nonce, b"attacker-key"));
+    }
+
+    #[test]
+    fn verify_judgment_rejects_a_replayed_nonce() {
+        let key = b"correct-horse-battery-staple";
+        let command = Command { action: "BLOCK".to_string(), redirect_target: None };
+        let judgment = signed_judgment(command, "original-nonce", key);
+
+        assert!(!verify_judgment(&judgment, "different-nonce", key));
+    }
+
+    #[test]
+    fn verify_judgment_rejects_a_tampered_command() {
+        let key = b"correct-horse-battery-staple";
+        let nonce = "nonce-1";
+        let judgment = signed_judgment(Command { action: "ALLOW".to_string(), redirect_target: None }, nonce, key);
+        let tampered = ThreatJudgment {
+            command: Command { action: "BLOCK".to_string(), redirect_target: None },
+            ..judgment
+        };
+
+        assert!(!verify_judgment(&tampered, nonce, key));
+    }
+
+    fn peer(ip: &str) -> SocketAddr {
+        SocketAddr::new(ip.parse().unwrap(), 12345)
+    }
+
+    #[test]
+    fn resolve_client_ip_ignores_forwarded_headers_when_untrusted() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.9".parse().unwrap());
+
+        assert_eq!(resolve_client_ip(peer("10.0.0.1"), &headers, false), peer("10.0.0.1").ip());
+    }
+
+    #[test]
+    fn resolve_client_ip_takes_the_rightmost_xff_entry_when_trusted() {
+        let mut headers = HeaderMap::new();
+        // Left-to-right is client -> ... -> our trusted LB; the right-most
+        // entry is the hop our LB actually observed.
+        headers.insert("x-forwarded-for", "203.0.113.9, 198.51.100.2".parse().unwrap());
+
+        assert_eq!(
+            resolve_client_ip(peer("10.0.0.1"), &headers, true),
+            "198.51.100.2".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn resolve_client_ip_falls_back_to_x_real_ip_when_trusted() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-real-ip", "203.0.113.9".parse().unwrap());
+
+        assert_eq!(
+            resolve_client_ip(peer("10.0.0.1"), &headers, true),
+            "203.0.113.9".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn resolve_client_ip_falls_back_to_peer_when_headers_are_unparseable() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "not-an-ip".parse().unwrap());
+
+        assert_eq!(resolve_client_ip(peer("10.0.0.1"), &headers, true), peer("10.0.0.1").ip());
+    }
 }
@@ -0,0 +1,191 @@
+// --- Resilience: retries and circuit breakers ---
+//
+// A single slow/dead backend used to block a handler forever (the
+// `reqwest::Client` had no timeout) and a failed brain call just logged and
+// fell through to forwarding -- a DoS on the brain silently disabled
+// deception. This adds bounded exponential-backoff retries for idempotent
+// calls plus a per-target circuit breaker: open after too many consecutive
+// failures to stop hammering a downed backend, half-open after a cooldown
+// to probe recovery.
+
+use metrics::gauge;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+
+const CLOSED: u8 = 0;
+const OPEN: u8 = 1;
+const HALF_OPEN: u8 = 2;
+
+pub struct CircuitBreaker {
+    name: &'static str,
+    failure_threshold: u32,
+    cooldown: Duration,
+    consecutive_failures: AtomicU32,
+    status: AtomicU8,
+    opened_at: Mutex<Option<Instant>>,
+    // Only the caller that wins the CAS in `is_call_permitted` gets to be
+    // the probe; everyone else sees the breaker as still-open until that
+    // probe resolves via `record_success`/`record_failure`.
+    half_open_probe_in_flight: AtomicBool,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &'static str, failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            name,
+            failure_threshold,
+            cooldown,
+            consecutive_failures: AtomicU32::new(0),
+            status: AtomicU8::new(CLOSED),
+            opened_at: Mutex::new(None),
+            half_open_probe_in_flight: AtomicBool::new(false),
+        }
+    }
+
+    /// Is a call to this target allowed right now? Closed: yes. Open: only
+    /// once the cooldown has elapsed, which flips the breaker to half-open.
+    /// Half-open: only the single caller that wins the probe-slot CAS below
+    /// -- everyone else is turned away until that probe resolves, so exactly
+    /// one call goes out instead of a thundering herd the instant the
+    /// cooldown expires.
+    pub async fn is_call_permitted(&self) -> bool {
+        match self.status.load(Ordering::Acquire) {
+            CLOSED => true,
+            HALF_OPEN => self
+                .half_open_probe_in_flight
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok(),
+            _ => {
+                let mut opened_at = self.opened_at.lock().await;
+                match *opened_at {
+                    Some(at) if at.elapsed() >= self.cooldown => {
+                        self.status.store(HALF_OPEN, Ordering::Release);
+                        *opened_at = None;
+                        self.half_open_probe_in_flight.store(true, Ordering::Release);
+                        self.record_state_metric();
+                        true
+                    }
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    pub async fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        self.status.store(CLOSED, Ordering::Release);
+        self.half_open_probe_in_flight.store(false, Ordering::Release);
+        *self.opened_at.lock().await = None;
+        self.record_state_metric();
+    }
+
+    pub async fn record_failure(&self) {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        // Release the probe slot unconditionally: whether or not this
+        // failure reopens the breaker, the in-flight probe has resolved and
+        // the next caller should be allowed to take its place.
+        self.half_open_probe_in_flight.store(false, Ordering::Release);
+        if failures >= self.failure_threshold {
+            self.status.store(OPEN, Ordering::Release);
+            *self.opened_at.lock().await = Some(Instant::now());
+        }
+        self.record_state_metric();
+    }
+
+    fn record_state_metric(&self) {
+        let value = match self.status.load(Ordering::Acquire) {
+            OPEN => 2.0,
+            HALF_OPEN => 1.0,
+            _ => 0.0,
+        };
+        gauge!("siren_circuit_state", "target" => self.name).set(value);
+    }
+}
+
+/// Bounded exponential-backoff retry. Only idempotent calls should be
+/// passed in here -- retrying a non-idempotent request (e.g. a POST with
+/// side effects) on a timeout can duplicate the effect.
+pub async fn retry_idempotent<F, Fut, T, E>(max_retries: u32, base_delay: Duration, mut call: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 0;
+    loop {
+        match call().await {
+            Ok(value) => return Ok(value),
+            Err(_) if attempt < max_retries => {
+                sleep(base_delay * 2u32.pow(attempt)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn closed_breaker_permits_calls_and_opens_after_threshold_failures() {
+        let breaker = CircuitBreaker::new("test", 3, Duration::from_secs(30));
+        assert!(breaker.is_call_permitted().await);
+
+        breaker.record_failure().await;
+        breaker.record_failure().await;
+        assert!(breaker.is_call_permitted().await, "below threshold, still closed");
+
+        breaker.record_failure().await;
+        assert!(!breaker.is_call_permitted().await, "threshold crossed, should be open");
+    }
+
+    #[tokio::test]
+    async fn open_breaker_stays_closed_to_calls_until_the_cooldown_elapses() {
+        let breaker = CircuitBreaker::new("test", 1, Duration::from_millis(50));
+        breaker.record_failure().await;
+        assert!(!breaker.is_call_permitted().await);
+
+        tokio::time::sleep(Duration::from_millis(75)).await;
+        assert!(breaker.is_call_permitted().await, "cooldown elapsed, should allow the probe");
+    }
+
+    #[tokio::test]
+    async fn only_one_half_open_probe_is_permitted_at_a_time() {
+        let breaker = CircuitBreaker::new("test", 1, Duration::from_millis(1));
+        breaker.record_failure().await;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        assert!(breaker.is_call_permitted().await, "first caller wins the probe slot");
+        assert!(!breaker.is_call_permitted().await, "second concurrent caller is turned away");
+        assert!(!breaker.is_call_permitted().await, "third concurrent caller is turned away too");
+    }
+
+    #[tokio::test]
+    async fn a_successful_probe_closes_the_breaker_and_frees_the_probe_slot() {
+        let breaker = CircuitBreaker::new("test", 1, Duration::from_millis(1));
+        breaker.record_failure().await;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert!(breaker.is_call_permitted().await);
+
+        breaker.record_success().await;
+        assert!(breaker.is_call_permitted().await, "closed again, any caller is let through");
+    }
+
+    #[tokio::test]
+    async fn a_failed_probe_reopens_the_breaker_and_frees_the_probe_slot_for_the_next_attempt() {
+        let breaker = CircuitBreaker::new("test", 1, Duration::from_millis(1));
+        breaker.record_failure().await;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert!(breaker.is_call_permitted().await);
+
+        breaker.record_failure().await;
+        assert!(!breaker.is_call_permitted().await, "back open immediately after a failed probe");
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert!(breaker.is_call_permitted().await, "next probe slot is available once the cooldown elapses again");
+    }
+}